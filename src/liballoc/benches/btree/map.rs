@@ -282,3 +282,12 @@ pub fn iter_10k(b: &mut Bencher) {
 pub fn iter_1m(b: &mut Bencher) {
     bench_iter(b, 1_000, 1_000_000);
 }
+
+// FIXME(frank-lesser/rust#chunk0-1): `BTreeMap` has no `Cursor`/`CursorMut`
+// API yet (no `lower_bound`, `move_next`/`move_prev`, `peek_next`/
+// `peek_prev`, or `insert_after`/`insert_before`/`remove_current`), and
+// adding one means cache-the-path traversal support in
+// `collections::btree::{map,node}`, neither of which is in this tree. A
+// `cursor_seek_then_scan` bench belongs here, mirroring `bench_range` above,
+// once that API lands -- not before, since there would be nothing real for
+// it to call or guard.
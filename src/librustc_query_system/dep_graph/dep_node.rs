@@ -45,7 +45,11 @@
 use super::{DepContext, DepKind};
 
 use rustc_data_structures::fingerprint::Fingerprint;
+use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::stable_hasher::{HashStable, StableHasher};
+use rustc_data_structures::sync::Lock;
+use rustc_serialize::opaque;
+use rustc_serialize::{Decodable, Encodable};
 
 use std::fmt;
 use std::hash::Hash;
@@ -80,6 +84,8 @@ impl<K: DepKind> DepNode<K> {
             }
         }
 
+        arg.recordable(tcx, dep_node);
+
         dep_node
     }
 }
@@ -112,6 +118,14 @@ pub trait DepNodeParams<Ctxt: DepContext>: fmt::Debug + Sized {
     /// It is always valid to return `None` here, in which case incremental
     /// compilation will treat the query as having changed instead of forcing it.
     fn recover(tcx: Ctxt, dep_node: &DepNode<Ctxt::DepKind>) -> Option<Self>;
+
+    /// Stashes this key under `dep_node`'s fingerprint in `tcx`'s
+    /// [`QueryKeyCache`], if it has one, so that a later [`recover`] can find
+    /// it again. This is a no-op by default: kinds that cannot be encoded, or
+    /// contexts that keep no cache, pay nothing for it.
+    ///
+    /// [`recover`]: DepNodeParams::recover
+    fn recordable(&self, _tcx: Ctxt, _dep_node: DepNode<Ctxt::DepKind>) {}
 }
 
 impl<Ctxt: DepContext, T> DepNodeParams<Ctxt> for T
@@ -141,6 +155,80 @@ where
     }
 }
 
+/// A side-table mapping a `DepNode`'s fingerprint back to the opaque-encoded
+/// form of the query key that produced it. Query kinds whose key is a single
+/// `RecoverableDepNodeKey` value (the common case of a `DefId`-like
+/// parameter) are recorded here as they are constructed, so that red-green
+/// forcing can recover the key instead of unconditionally treating the query
+/// as changed. The table lives in memory only: it is not written to or read
+/// from disk, so only keys recorded earlier in the same compilation session
+/// can be recovered.
+///
+/// A missing entry is always a safe, if conservative, outcome: it just means
+/// the corresponding `DepNodeParams::recover` falls back to `None`, the same
+/// as if this cache did not exist at all.
+#[derive(Default)]
+pub struct QueryKeyCache {
+    keys: Lock<FxHashMap<Fingerprint, Vec<u8>>>,
+}
+
+impl QueryKeyCache {
+    fn insert(&self, hash: Fingerprint, encode: impl FnOnce(&mut opaque::Encoder)) {
+        let mut encoder = opaque::Encoder::new(Vec::new());
+        encode(&mut encoder);
+        self.keys.borrow_mut().entry(hash).or_insert_with(|| encoder.into_inner());
+    }
+
+    fn get(&self, hash: Fingerprint) -> Option<Vec<u8>> {
+        self.keys.borrow().get(&hash).cloned()
+    }
+}
+
+/// Implemented by query contexts whose dep-graph can hand back the
+/// [`QueryKeyCache`] used to recover forced `DepNode`s. Entries are inserted
+/// eagerly by `DepNode::construct`, so such a context pays for encoding every
+/// eligible key it constructs whether or not that node is later forced;
+/// nothing loads the table from elsewhere. Kept separate from [`DepContext`] so that contexts
+/// without such a cache simply don't implement this and every
+/// `DepNodeParams` specialized on it falls back to the conservative default.
+pub trait HasQueryKeyCache: DepContext {
+    fn query_key_cache(&self) -> &QueryKeyCache;
+}
+
+/// Marker for query keys that are a single, self-contained identifier -- the
+/// common "just a `DefId`" case -- rather than a compound key assembled from
+/// several of them. Only these are eligible for the `QueryKeyCache`
+/// specialization below: a `(DefId, DefId)` or `Vec<DefId>` key is just as
+/// `Encodable`/`Decodable`, but recovering it from nothing but its
+/// fingerprint would mean splitting the encoded bytes back into the right
+/// number of parts, which this sidesteps by requiring each eligible key type
+/// to opt in explicitly instead of picking up every `Encodable` type via a
+/// blanket impl.
+pub trait RecoverableDepNodeKey {}
+
+impl<Ctxt, T> DepNodeParams<Ctxt> for T
+where
+    Ctxt: HasQueryKeyCache,
+    T: HashStable<Ctxt::StableHashingContext> + fmt::Debug + Encodable + Decodable
+        + RecoverableDepNodeKey,
+{
+    default fn can_reconstruct_query_key() -> bool {
+        true
+    }
+
+    default fn recordable(&self, tcx: Ctxt, dep_node: DepNode<Ctxt::DepKind>) {
+        tcx.query_key_cache().insert(dep_node.hash, |encoder| {
+            self.encode(encoder).expect("failed to encode dep-node key")
+        });
+    }
+
+    default fn recover(tcx: Ctxt, dep_node: &DepNode<Ctxt::DepKind>) -> Option<Self> {
+        let bytes = tcx.query_key_cache().get(dep_node.hash)?;
+        let mut decoder = opaque::Decoder::new(&bytes, 0);
+        Self::decode(&mut decoder).ok()
+    }
+}
+
 impl<Ctxt: DepContext> DepNodeParams<Ctxt> for () {
     fn to_fingerprint(&self, _: Ctxt) -> Fingerprint {
         Fingerprint::ZERO
@@ -4,38 +4,71 @@
 // #[test_case] is used by custom test authors to mark tests
 // When building for test, it needs to make the item public and gensym the name
 // Otherwise, we'll omit the item. This behavior means that any item annotated
-// with #[test_case] is never addressable.
+// with #[test_case] is never addressable, unless `name = "..."` is given, in
+// which case we keep the item's own ident instead of gensym'ing it.
 //
 // We mark item with an inert attribute "rustc_test_marker" which the test generation
-// logic will pick up on.
+// logic will pick up on. When a name was requested, that name is recorded as the
+// marker's value so custom test frameworks can filter or group on it.
 
 use syntax::ext::base::*;
 use syntax::ext::build::AstBuilder;
 use syntax::ext::hygiene::SyntaxContext;
 use syntax::ast;
 use syntax::source_map::respan;
-use syntax::symbol::sym;
+use syntax::symbol::{sym, Symbol};
 use syntax_pos::Span;
 
 pub fn expand(
     ecx: &mut ExtCtxt<'_>,
     attr_sp: Span,
-    _meta_item: &ast::MetaItem,
+    meta_item: &ast::MetaItem,
     anno_item: Annotatable
 ) -> Vec<Annotatable> {
     if !ecx.ecfg.should_test { return vec![]; }
 
     let sp = attr_sp.with_ctxt(SyntaxContext::empty().apply_mark(ecx.current_expansion.mark));
+    let name = test_case_name(ecx, meta_item);
     let mut item = anno_item.expect_item();
     item = item.map(|mut item| {
         item.vis = respan(item.vis.span, ast::VisibilityKind::Public);
-        item.ident = item.ident.gensym();
-        item.attrs.push(
-            ecx.attribute(sp,
-                ecx.meta_word(sp, sym::rustc_test_marker))
-        );
+        let marker = match name {
+            Some(name) => {
+                let lit = ast::LitKind::Str(name, ast::StrStyle::Cooked);
+                ecx.meta_name_value(sp, sym::rustc_test_marker, lit)
+            }
+            None => {
+                item.ident = item.ident.gensym();
+                ecx.meta_word(sp, sym::rustc_test_marker)
+            }
+        };
+        item.attrs.push(ecx.attribute(sp, marker));
         item
     });
 
     return vec![Annotatable::Item(item)]
 }
+
+/// Pulls the `name` out of a `#[test_case(name = "...")]` argument, if one
+/// was given. Returns `None` for bare `#[test_case]`, in which case the
+/// caller falls back to gensym'ing the item as before.
+fn test_case_name(ecx: &ExtCtxt<'_>, meta_item: &ast::MetaItem) -> Option<Symbol> {
+    let list = meta_item.meta_item_list()?;
+    for nested in list {
+        let item = match nested.meta_item() {
+            Some(item) => item,
+            // Not itself a `key = value`/`key(..)` meta item (e.g. a bare
+            // literal) -- it can't be our `name`, so skip it and keep
+            // scanning the rest of the list.
+            None => continue,
+        };
+        if item.check_name(sym::name) {
+            let name = item.value_str();
+            if name.is_none() {
+                ecx.span_err(item.span, "expected `name = \"...\"`");
+            }
+            return name;
+        }
+    }
+    None
+}